@@ -0,0 +1,142 @@
+use futures::stream::StreamExt;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+use crate::pubsub::flow::FlowControlOptions;
+use crate::pubsub::{Message, Subscription};
+
+/// Fans out a single subscription's streaming pull to any number of
+/// in-process consumers, so only one server-side streaming connection is
+/// held regardless of how many subscribers read from it. Each subscriber
+/// gets its own [`BroadcastSubscriber`] handle and acks independently;
+/// since every `Message` carries its own ack handle, acks still go straight
+/// back to Pub/Sub no matter which subscriber issued them (the same is true
+/// of every other path that hands out a `Message`, streaming or not).
+pub struct SubscriptionBroadcaster {
+    sender: broadcast::Sender<Message>,
+    task: JoinHandle<()>,
+}
+
+impl SubscriptionBroadcaster {
+    /// Start broadcasting `subscription`'s messages. `capacity` bounds how
+    /// far a slow subscriber may lag behind before it starts missing
+    /// messages (see [`tokio::sync::broadcast::channel`]).
+    pub fn new(subscription: Subscription, capacity: usize) -> SubscriptionBroadcaster {
+        let (sender, _) = broadcast::channel(capacity);
+        let task_sender = sender.clone();
+        let task = tokio::spawn(Self::run(subscription, task_sender));
+
+        SubscriptionBroadcaster { sender, task }
+    }
+
+    async fn run(mut subscription: Subscription, sender: broadcast::Sender<Message>) {
+        // A single streaming pull backs every fanned-out subscriber, instead
+        // of each polling the unary `Pull` RPC independently.
+        let mut stream = match subscription.subscribe(FlowControlOptions::default()).await {
+            Ok(stream) => stream,
+            Err(_) => return,
+        };
+
+        while let Some(Ok(message)) = stream.next().await {
+            // No subscribers currently listening; drop the message.
+            let _ = sender.send(message);
+        }
+    }
+
+    /// Get a new handle to this broadcaster's messages.
+    pub fn subscribe(&self) -> BroadcastSubscriber {
+        BroadcastSubscriber {
+            receiver: self.sender.subscribe(),
+        }
+    }
+}
+
+impl Drop for SubscriptionBroadcaster {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// One in-process consumer's view of a [`SubscriptionBroadcaster`].
+///
+/// Generic over the broadcast item type `T` (production code uses the
+/// default, [`Message`]) so the lag-skipping `recv` logic can be
+/// unit-tested against a plain `tokio::sync::broadcast` channel without
+/// constructing a real `Message`.
+pub struct BroadcastSubscriber<T = Message> {
+    receiver: broadcast::Receiver<T>,
+}
+
+impl<T: Clone> BroadcastSubscriber<T> {
+    /// Receive the next message, transparently skipping ahead if this
+    /// subscriber fell too far behind to receive every message.
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(message) => return Some(message),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+impl<T> Clone for BroadcastSubscriber<T> {
+    fn clone(&self) -> Self {
+        BroadcastSubscriber {
+            receiver: self.receiver.resubscribe(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn recv_returns_sent_messages_in_order() {
+        let (tx, rx) = broadcast::channel(8);
+        let mut subscriber = BroadcastSubscriber { receiver: rx };
+
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+
+        assert_eq!(subscriber.recv().await, Some(1));
+        assert_eq!(subscriber.recv().await, Some(2));
+    }
+
+    #[tokio::test]
+    async fn recv_returns_none_once_every_sender_is_dropped() {
+        let (tx, rx) = broadcast::channel::<u32>(8);
+        let mut subscriber = BroadcastSubscriber { receiver: rx };
+        drop(tx);
+
+        assert_eq!(subscriber.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn recv_skips_past_lagged_messages_instead_of_erroring() {
+        let (tx, rx) = broadcast::channel(2);
+        let mut subscriber = BroadcastSubscriber { receiver: rx };
+
+        // Overflow the channel's capacity so the receiver lags.
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        tx.send(3).unwrap();
+
+        // The lag is skipped transparently; the oldest survivor comes back.
+        assert_eq!(subscriber.recv().await, Some(2));
+        assert_eq!(subscriber.recv().await, Some(3));
+    }
+
+    #[tokio::test]
+    async fn clone_gets_an_independent_receiver() {
+        let (tx, rx) = broadcast::channel(8);
+        let subscriber = BroadcastSubscriber { receiver: rx };
+        let mut clone = subscriber.clone();
+
+        tx.send(1).unwrap();
+        assert_eq!(clone.recv().await, Some(1));
+    }
+}
+
@@ -0,0 +1,143 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::task::Waker;
+
+/// Flow-control limits for a [`crate::pubsub::Subscription::subscribe`]
+/// stream: the stream stops asking the server for more messages once either
+/// limit is reached, and resumes once enough messages have been acked or
+/// nacked to fall back under both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowControlOptions {
+    /// The maximum number of messages that may be outstanding (received but
+    /// not yet acked/nacked) at once.
+    pub max_outstanding_messages: usize,
+    /// The maximum total size, in bytes, of outstanding messages' data.
+    pub max_outstanding_bytes: usize,
+}
+
+impl Default for FlowControlOptions {
+    fn default() -> Self {
+        FlowControlOptions {
+            max_outstanding_messages: 1000,
+            max_outstanding_bytes: 100 * 1024 * 1024,
+        }
+    }
+}
+
+/// Tracks outstanding messages/bytes for a flow-controlled stream and wakes
+/// the stream's task back up once it falls under its limits.
+#[derive(Debug)]
+pub(crate) struct FlowController {
+    pub(crate) options: FlowControlOptions,
+    messages: AtomicUsize,
+    bytes: AtomicUsize,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl FlowController {
+    pub(crate) fn new(options: FlowControlOptions) -> FlowController {
+        FlowController {
+            options,
+            messages: AtomicUsize::new(0),
+            bytes: AtomicUsize::new(0),
+            waker: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn has_capacity(&self) -> bool {
+        self.messages.load(Ordering::Acquire) < self.options.max_outstanding_messages
+            && self.bytes.load(Ordering::Acquire) < self.options.max_outstanding_bytes
+    }
+
+    pub(crate) fn reserve(&self, size: usize) {
+        self.messages.fetch_add(1, Ordering::AcqRel);
+        self.bytes.fetch_add(size, Ordering::AcqRel);
+    }
+
+    /// Release a previously reserved message and wake the stream task if
+    /// there's one parked waiting for capacity.
+    pub(crate) fn release(&self, size: usize) {
+        self.messages.fetch_sub(1, Ordering::AcqRel);
+        self.bytes.fetch_sub(size, Ordering::AcqRel);
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+
+    /// Register `waker` to be woken by a future `release()`, then check
+    /// capacity again. Registering before the (re-)check closes the
+    /// lost-wakeup window: if a `release()` races with this call, it either
+    /// happens before the check (so `has_capacity()` already reflects it)
+    /// or after the waker is stored (so it wakes us instead of finding
+    /// nothing registered).
+    pub(crate) fn poll_capacity(&self, waker: &Waker) -> bool {
+        *self.waker.lock().unwrap() = Some(waker.clone());
+        self.has_capacity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    struct FlagWaker(AtomicBool);
+
+    impl Wake for FlagWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn controller(max_messages: usize, max_bytes: usize) -> FlowController {
+        FlowController::new(FlowControlOptions {
+            max_outstanding_messages: max_messages,
+            max_outstanding_bytes: max_bytes,
+        })
+    }
+
+    #[test]
+    fn has_capacity_respects_both_limits() {
+        let flow = controller(3, 100);
+        assert!(flow.has_capacity());
+
+        flow.reserve(50);
+        assert!(flow.has_capacity());
+
+        flow.reserve(60);
+        assert!(!flow.has_capacity(), "byte limit should be exceeded");
+    }
+
+    #[test]
+    fn has_capacity_respects_message_count_limit() {
+        let flow = controller(1, 1000);
+        flow.reserve(1);
+        assert!(!flow.has_capacity(), "message limit should be exceeded");
+    }
+
+    #[test]
+    fn release_wakes_a_registered_waker() {
+        let flow = controller(1, 1000);
+        flow.reserve(1);
+
+        let flag = Arc::new(FlagWaker(AtomicBool::new(false)));
+        let waker = Waker::from(flag.clone());
+        assert!(!flow.poll_capacity(&waker));
+
+        flow.release(1);
+        assert!(flag.0.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn poll_capacity_sees_capacity_freed_before_registration() {
+        let flow = controller(1, 1000);
+        flow.reserve(1);
+        flow.release(1);
+
+        let flag = Arc::new(FlagWaker(AtomicBool::new(false)));
+        let waker = Waker::from(flag.clone());
+        assert!(flow.poll_capacity(&waker));
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration as StdDuration, Instant};
+
+use crate::pubsub::api;
+use crate::pubsub::Client;
+
+/// The Pub/Sub-imposed ceiling on how far an ack deadline may be extended in
+/// a single `ModifyAckDeadline` call.
+const MAX_ACK_DEADLINE_SECONDS: i32 = 600;
+
+/// How far ahead of expiry a lease is renewed, expressed as a fraction of
+/// the initial ack deadline.
+const RENEWAL_FRACTION: f64 = 0.5;
+
+struct Lease {
+    received_at: Instant,
+    deadline: StdDuration,
+}
+
+/// The pure bookkeeping behind [`LeaseManager`]: which ack IDs are
+/// outstanding, how long they've taken to ack historically, and which are
+/// due for renewal right now. Kept free of any RPC client so it can be
+/// exercised directly in tests.
+#[derive(Default)]
+struct LeaseTracker {
+    leases: Mutex<HashMap<String, Lease>>,
+    latencies: Mutex<Vec<StdDuration>>,
+}
+
+impl LeaseTracker {
+    fn track(&self, ack_id: String, initial_deadline: StdDuration) {
+        self.leases.lock().unwrap().insert(
+            ack_id,
+            Lease {
+                received_at: Instant::now(),
+                deadline: initial_deadline,
+            },
+        );
+    }
+
+    fn remove(&self, ack_id: &str) {
+        if let Some(lease) = self.leases.lock().unwrap().remove(ack_id) {
+            let latency = Instant::now().duration_since(lease.received_at);
+            let mut latencies = self.latencies.lock().unwrap();
+            latencies.push(latency);
+            // Bound memory use; only the recent tail influences the percentile.
+            if latencies.len() > 1000 {
+                latencies.remove(0);
+            }
+        }
+    }
+
+    /// The 99th-percentile observed ack latency, clamped to the Pub/Sub max,
+    /// falling back to `initial_deadline` when nothing has been observed yet.
+    fn extension(&self, initial_deadline: StdDuration) -> StdDuration {
+        let latencies = self.latencies.lock().unwrap();
+        if latencies.is_empty() {
+            return initial_deadline;
+        }
+
+        let mut sorted: Vec<StdDuration> = latencies.clone();
+        sorted.sort();
+        let index = ((sorted.len() as f64) * 0.99).ceil() as usize;
+        let index = index.saturating_sub(1).min(sorted.len() - 1);
+        let p99 = sorted[index];
+
+        p99.min(StdDuration::from_secs(MAX_ACK_DEADLINE_SECONDS as u64))
+    }
+
+    /// The ack IDs whose renewal window has been entered as of `now`
+    /// (extending their recorded deadline in place), minus any that have
+    /// been outstanding longer than `max_total_lease` and are dropped
+    /// instead. Returns the due IDs alongside the extension applied to them.
+    fn due(
+        &self,
+        now: Instant,
+        initial_deadline: StdDuration,
+        max_total_lease: StdDuration,
+    ) -> (Vec<String>, StdDuration) {
+        // Never extend by less than the initial deadline: a shorter (e.g.
+        // sub-second) extension would lapse before the next renewal tick.
+        let extension = self.extension(initial_deadline).max(initial_deadline);
+        let renewal_window = initial_deadline.mul_f64(RENEWAL_FRACTION);
+
+        let mut leases = self.leases.lock().unwrap();
+        let mut due = Vec::new();
+        leases.retain(|ack_id, lease| {
+            if now.duration_since(lease.received_at) >= max_total_lease {
+                // Give up on this message; let it expire and be redelivered.
+                return false;
+            }
+            let elapsed = now.duration_since(lease.received_at);
+            if lease.deadline.saturating_sub(elapsed) <= renewal_window {
+                due.push(ack_id.clone());
+                lease.deadline = elapsed + extension;
+            }
+            true
+        });
+
+        (due, extension)
+    }
+}
+
+struct Inner {
+    client: Client,
+    subscription_name: String,
+    initial_deadline: StdDuration,
+    max_total_lease: StdDuration,
+    tracker: LeaseTracker,
+}
+
+impl Inner {
+    async fn renew_due_leases(&self) {
+        let (due, extension) = self
+            .tracker
+            .due(Instant::now(), self.initial_deadline, self.max_total_lease);
+
+        if due.is_empty() {
+            return;
+        }
+
+        let request = api::ModifyAckDeadlineRequest {
+            subscription: self.subscription_name.clone(),
+            ack_ids: due,
+            ack_deadline_seconds: extension.as_secs() as i32,
+        };
+        if let Ok(request) = self.client.clone().construct_request(request).await {
+            let _ = self.client.clone().subscriber.modify_ack_deadline(request).await;
+        }
+    }
+}
+
+/// Keeps outstanding messages alive by renewing their ack deadline in the
+/// background, so handlers that outlive the default 10s window aren't
+/// redelivered. Renewal length is adaptive: it tracks observed ack
+/// latencies and extends by their 99th percentile, never less than the
+/// initial ack deadline and clamped to the Pub/Sub maximum of 600s.
+pub struct LeaseManager {
+    inner: Arc<Inner>,
+    task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
+
+impl std::fmt::Debug for LeaseManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LeaseManager")
+            .field("subscription_name", &self.inner.subscription_name)
+            .finish()
+    }
+}
+
+impl LeaseManager {
+    pub(crate) fn new(
+        client: Client,
+        subscription_name: String,
+        initial_deadline: StdDuration,
+        max_total_lease: StdDuration,
+    ) -> LeaseManager {
+        LeaseManager {
+            inner: Arc::new(Inner {
+                client,
+                subscription_name,
+                initial_deadline,
+                max_total_lease,
+                tracker: LeaseTracker::default(),
+            }),
+            task: Mutex::new(None),
+        }
+    }
+
+    /// Start tracking ack deadline for `ack_id`, issuing renewals until it's
+    /// removed with [`LeaseManager::remove`].
+    pub fn track(&self, ack_id: impl Into<String>) {
+        self.inner.tracker.track(ack_id.into(), self.inner.initial_deadline);
+    }
+
+    /// Stop tracking `ack_id`, recording how long it took to ack/nack so
+    /// future renewal windows can adapt to it.
+    pub fn remove(&self, ack_id: &str) {
+        self.inner.tracker.remove(ack_id);
+    }
+
+    /// Start the background renewal loop.
+    pub fn start(&self) {
+        let mut task = self.task.lock().unwrap();
+        if task.is_some() {
+            return;
+        }
+
+        let inner = self.inner.clone();
+        let tick = inner.initial_deadline.mul_f64(RENEWAL_FRACTION);
+        *task = Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tick);
+            loop {
+                interval.tick().await;
+                inner.renew_due_leases().await;
+            }
+        }));
+    }
+
+    /// Stop the background renewal loop. Already-tracked leases are kept
+    /// around but no longer renewed.
+    pub fn stop(&self) {
+        if let Some(task) = self.task.lock().unwrap().take() {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for LeaseManager {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extension_falls_back_to_initial_deadline_with_no_history() {
+        let tracker = LeaseTracker::default();
+        assert_eq!(
+            tracker.extension(StdDuration::from_secs(10)),
+            StdDuration::from_secs(10)
+        );
+    }
+
+    #[test]
+    fn extension_is_the_99th_percentile_of_observed_latencies() {
+        let tracker = LeaseTracker::default();
+        for ms in 1..=100u64 {
+            tracker.latencies.lock().unwrap().push(StdDuration::from_millis(ms));
+        }
+
+        // The 99th percentile of 1ms..=100ms is the 99th smallest value.
+        assert_eq!(tracker.extension(StdDuration::from_secs(10)), StdDuration::from_millis(99));
+    }
+
+    #[test]
+    fn extension_is_clamped_to_pubsub_max() {
+        let tracker = LeaseTracker::default();
+        tracker
+            .latencies
+            .lock()
+            .unwrap()
+            .push(StdDuration::from_secs(MAX_ACK_DEADLINE_SECONDS as u64 + 100));
+
+        assert_eq!(
+            tracker.extension(StdDuration::from_secs(10)),
+            StdDuration::from_secs(MAX_ACK_DEADLINE_SECONDS as u64)
+        );
+    }
+
+    #[test]
+    fn due_renews_leases_within_the_renewal_window() {
+        let tracker = LeaseTracker::default();
+        let initial_deadline = StdDuration::from_secs(10);
+        tracker.track("in-window".into(), initial_deadline);
+        tracker.track("fresh".into(), initial_deadline);
+
+        // Pretend "in-window" was received 8s ago (2s left, under the 5s
+        // renewal window) and "fresh" was just received.
+        {
+            let mut leases = tracker.leases.lock().unwrap();
+            leases.get_mut("in-window").unwrap().received_at =
+                Instant::now() - StdDuration::from_secs(8);
+        }
+
+        let (due, _extension) = tracker.due(Instant::now(), initial_deadline, StdDuration::from_secs(600));
+        assert_eq!(due, vec!["in-window".to_string()]);
+    }
+
+    #[test]
+    fn due_drops_leases_past_the_max_total_lease() {
+        let tracker = LeaseTracker::default();
+        let initial_deadline = StdDuration::from_secs(10);
+        tracker.track("expired".into(), initial_deadline);
+        {
+            let mut leases = tracker.leases.lock().unwrap();
+            leases.get_mut("expired").unwrap().received_at =
+                Instant::now() - StdDuration::from_secs(700);
+        }
+
+        let (due, _extension) = tracker.due(Instant::now(), initial_deadline, StdDuration::from_secs(600));
+        assert!(due.is_empty());
+        assert!(!tracker.leases.lock().unwrap().contains_key("expired"));
+    }
+}
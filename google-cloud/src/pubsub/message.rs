@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+
+use crate::pubsub::flow::FlowController;
+use crate::pubsub::lease::LeaseManager;
+use crate::pubsub::order::OrderingController;
+use crate::pubsub::schema::{DecodeError, SchemaConfig, SchemaEncoding, SchemaType};
+use crate::pubsub::{api, Client, Error};
+
+/// Represents a message.
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub(crate) client: Client,
+    pub(crate) subscription_name: String,
+    pub(crate) data: Vec<u8>,
+    pub(crate) message_id: String,
+    pub(crate) ack_id: String,
+    pub(crate) attributes: HashMap<String, String>,
+    pub(crate) publish_time: NaiveDateTime,
+    pub(crate) delivery_attempt: i32,
+    pub(crate) ordering_key: Option<String>,
+    pub(crate) schema: Option<Arc<SchemaConfig>>,
+    pub(crate) lease: Option<Arc<LeaseManager>>,
+    pub(crate) flow: Option<Arc<FlowController>>,
+    pub(crate) ordering: Option<Arc<OrderingController>>,
+}
+
+impl Message {
+    /// The message's unique identifier within its topic.
+    pub fn id(&self) -> &str {
+        &self.message_id
+    }
+
+    /// The message's binary payload.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The message's attributes.
+    pub fn attributes(&self) -> &HashMap<String, String> {
+        &self.attributes
+    }
+
+    /// The time the message was published.
+    pub fn publish_time(&self) -> NaiveDateTime {
+        self.publish_time
+    }
+
+    /// How many times this message has been delivered so far. Only
+    /// populated when the subscription has a dead-letter policy configured;
+    /// `None` otherwise, so handlers can tell "not tracked" apart from
+    /// "first delivery".
+    pub fn delivery_attempt(&self) -> Option<i32> {
+        delivery_attempt_from_raw(self.delivery_attempt)
+    }
+
+    /// The ordering key this message was published with, if any.
+    pub fn ordering_key(&self) -> Option<&str> {
+        self.ordering_key.as_deref()
+    }
+
+    /// Acknowledge the message, indicating it can be removed from the subscription.
+    pub async fn ack(self) -> Result<(), Error> {
+        if let Some(lease) = &self.lease {
+            lease.remove(&self.ack_id);
+        }
+        if let Some(flow) = &self.flow {
+            flow.release(self.data.len());
+        }
+        if let (Some(ordering), Some(key)) = (&self.ordering, &self.ordering_key) {
+            ordering.complete(key);
+        }
+
+        let request = api::AcknowledgeRequest {
+            subscription: self.subscription_name,
+            ack_ids: vec![self.ack_id],
+        };
+        let mut client = self.client;
+        let request = client.construct_request(request).await?;
+        client.subscriber.acknowledge(request).await?;
+
+        Ok(())
+    }
+
+    /// Decline the message, making it immediately eligible for redelivery.
+    pub async fn nack(self) -> Result<(), Error> {
+        if let Some(lease) = &self.lease {
+            lease.remove(&self.ack_id);
+        }
+        if let Some(flow) = &self.flow {
+            flow.release(self.data.len());
+        }
+        if let (Some(ordering), Some(key)) = (&self.ordering, &self.ordering_key) {
+            ordering.complete(key);
+        }
+
+        let request = api::ModifyAckDeadlineRequest {
+            subscription: self.subscription_name,
+            ack_ids: vec![self.ack_id],
+            ack_deadline_seconds: 0,
+        };
+        let mut client = self.client;
+        let request = client.construct_request(request).await?;
+        client.subscriber.modify_ack_deadline(request).await?;
+
+        Ok(())
+    }
+
+    /// Decode the message's data as an Avro record, validating it against the
+    /// schema bound to the subscription it was received on.
+    pub fn decode_avro(&self) -> Result<apache_avro::types::Value, DecodeError> {
+        let schema = self.schema.as_deref().ok_or(DecodeError::NoSchema)?;
+        if schema.schema_type() != SchemaType::Avro {
+            return Err(DecodeError::WrongSchemaType);
+        }
+
+        let avro_schema = apache_avro::Schema::parse_str(schema.definition())
+            .map_err(|err| DecodeError::Invalid(err.to_string()))?;
+
+        match SchemaEncoding::from_attributes(&self.attributes).ok_or(DecodeError::UnknownEncoding)? {
+            SchemaEncoding::Binary => {
+                let mut reader = &self.data[..];
+                apache_avro::from_avro_datum(&avro_schema, &mut reader, None)
+                    .map_err(|err| DecodeError::Invalid(err.to_string()))
+            }
+            SchemaEncoding::Json => {
+                let json: serde_json::Value =
+                    serde_json::from_slice(&self.data).map_err(|err| DecodeError::Invalid(err.to_string()))?;
+                apache_avro::types::Value::from(json)
+                    .resolve(&avro_schema)
+                    .map_err(|err| DecodeError::Invalid(err.to_string()))
+            }
+        }
+    }
+
+    /// Decode the message's data as a Protocol Buffer message of type `T`,
+    /// validating it against the schema bound to the subscription it was
+    /// received on.
+    ///
+    /// `T` must be reflection-capable (`prost_reflect::ReflectMessage`, as
+    /// emitted by `prost-reflect-build` alongside the usual `prost-build`
+    /// codegen). The JSON branch decodes through `T::descriptor()` via
+    /// `prost_reflect`'s `DynamicMessage`, which implements the canonical
+    /// protobuf JSON mapping (string enums, 64-bit ints as decimal strings,
+    /// base64 bytes, `oneof`/well-known-type handling) — a plain
+    /// `serde::Deserialize` derive on `T` would not reproduce that mapping.
+    pub fn decode_proto<T>(&self) -> Result<T, DecodeError>
+    where
+        T: prost::Message + prost_reflect::ReflectMessage + Default,
+    {
+        let schema = self.schema.as_deref().ok_or(DecodeError::NoSchema)?;
+        if schema.schema_type() != SchemaType::ProtocolBuffer {
+            return Err(DecodeError::WrongSchemaType);
+        }
+
+        match SchemaEncoding::from_attributes(&self.attributes).ok_or(DecodeError::UnknownEncoding)? {
+            SchemaEncoding::Binary => {
+                T::decode(&self.data[..]).map_err(|err| DecodeError::Invalid(err.to_string()))
+            }
+            SchemaEncoding::Json => {
+                let mut deserializer = serde_json::Deserializer::from_slice(&self.data);
+                let dynamic = prost_reflect::DynamicMessage::deserialize(
+                    T::default().descriptor(),
+                    &mut deserializer,
+                )
+                .map_err(|err| DecodeError::Invalid(err.to_string()))?;
+                T::decode(dynamic.encode_to_vec().as_slice())
+                    .map_err(|err| DecodeError::Invalid(err.to_string()))
+            }
+        }
+    }
+}
+
+/// The Pub/Sub wire representation uses `0` for "not tracked" rather than
+/// an absent field, so this maps that back onto an `Option`.
+fn delivery_attempt_from_raw(raw: i32) -> Option<i32> {
+    if raw > 0 {
+        Some(raw)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_means_not_tracked() {
+        assert_eq!(delivery_attempt_from_raw(0), None);
+    }
+
+    #[test]
+    fn positive_values_pass_through() {
+        assert_eq!(delivery_attempt_from_raw(1), Some(1));
+        assert_eq!(delivery_attempt_from_raw(5), Some(5));
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+
+use crate::pubsub::Message;
+
+struct State<T> {
+    /// Buffered items waiting on their key's in-flight predecessor.
+    queues: HashMap<String, VecDeque<T>>,
+    /// Keys with an item currently out for processing.
+    in_flight: HashSet<String>,
+    /// Items released by a completed key, waiting to be handed out.
+    ready: VecDeque<T>,
+}
+
+impl<T> Default for State<T> {
+    fn default() -> Self {
+        State {
+            queues: HashMap::new(),
+            in_flight: HashSet::new(),
+            ready: VecDeque::new(),
+        }
+    }
+}
+
+/// Guarantees that items sharing an ordering key are handed to the consumer
+/// one at a time, in publish order: the next item for a key isn't released
+/// until the prior one has been acked or nacked.
+///
+/// Items with no ordering key pass straight through.
+///
+/// Generic over the buffered item type `T` (production code uses the
+/// default, [`Message`]) so the sequencing logic can be unit-tested without
+/// constructing real messages.
+///
+/// All bookkeeping lives behind a single mutex: `offer` (called as new
+/// items arrive) and `complete` (called from `Message::ack`/`nack`,
+/// typically on a different task) must never be able to deadlock against
+/// each other, and a single lock per call makes that true by construction.
+pub(crate) struct OrderingController<T = Message> {
+    state: Mutex<State<T>>,
+}
+
+impl<T> Default for OrderingController<T> {
+    fn default() -> Self {
+        OrderingController {
+            state: Mutex::new(State::default()),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for OrderingController<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OrderingController").finish_non_exhaustive()
+    }
+}
+
+impl<T> OrderingController<T> {
+    pub(crate) fn new() -> OrderingController<T> {
+        OrderingController::default()
+    }
+
+    /// Offer a newly received item under `key`. Returns it back if it can be
+    /// delivered immediately (no key, or its key has no item in flight);
+    /// otherwise buffers it behind its key's predecessor.
+    pub(crate) fn offer(&self, key: Option<String>, item: T) -> Option<T> {
+        let key = match key {
+            Some(key) => key,
+            None => return Some(item),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if state.in_flight.contains(&key) {
+            state.queues.entry(key).or_default().push_back(item);
+            None
+        } else {
+            state.in_flight.insert(key);
+            Some(item)
+        }
+    }
+
+    /// Mark `key`'s in-flight item as done, releasing the next buffered item
+    /// for that key (if any) into the ready queue.
+    pub(crate) fn complete(&self, key: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(next) = state.queues.get_mut(key).and_then(VecDeque::pop_front) {
+            state.ready.push_back(next);
+        } else {
+            state.in_flight.remove(key);
+        }
+    }
+
+    /// Take the next item released by a completed key, if any.
+    pub(crate) fn next_ready(&self) -> Option<T> {
+        self.state.lock().unwrap().ready.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyed(key: &str, seq: u32) -> (Option<String>, (String, u32)) {
+        (Some(key.to_string()), (key.to_string(), seq))
+    }
+
+    #[test]
+    fn unkeyed_items_pass_straight_through() {
+        let ordering = OrderingController::<u32>::new();
+        assert_eq!(ordering.offer(None, 1), Some(1));
+        assert_eq!(ordering.next_ready(), None);
+    }
+
+    #[test]
+    fn first_item_for_a_key_is_delivered_immediately() {
+        let ordering = OrderingController::new();
+        let (key, item) = keyed("a", 1);
+        assert_eq!(ordering.offer(key, item.clone()), Some(item));
+    }
+
+    #[test]
+    fn second_item_for_an_in_flight_key_is_buffered_until_complete() {
+        let ordering = OrderingController::new();
+        let (key1, item1) = keyed("a", 1);
+        let (key2, item2) = keyed("a", 2);
+
+        assert_eq!(ordering.offer(key1, item1), Some(("a".to_string(), 1)));
+        assert_eq!(ordering.offer(key2, item2.clone()), None);
+        assert_eq!(ordering.next_ready(), None);
+
+        ordering.complete("a");
+        assert_eq!(ordering.next_ready(), Some(item2));
+    }
+
+    #[test]
+    fn completing_a_key_with_nothing_buffered_frees_it_for_a_new_item() {
+        let ordering = OrderingController::new();
+        let (key1, item1) = keyed("a", 1);
+        ordering.offer(key1, item1);
+        ordering.complete("a");
+
+        let (key2, item2) = keyed("a", 2);
+        assert_eq!(ordering.offer(key2, item2.clone()), Some(item2));
+    }
+
+    #[test]
+    fn different_keys_are_independent() {
+        let ordering = OrderingController::new();
+        let (key_a, item_a) = keyed("a", 1);
+        let (key_b, item_b) = keyed("b", 1);
+
+        assert_eq!(ordering.offer(key_a, item_a.clone()), Some(item_a));
+        assert_eq!(ordering.offer(key_b, item_b.clone()), Some(item_b));
+    }
+}
@@ -0,0 +1,82 @@
+use std::fmt;
+
+/// The format a schema is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaType {
+    /// The schema is a Protocol Buffer descriptor.
+    ProtocolBuffer,
+    /// The schema is an Avro schema.
+    Avro,
+}
+
+/// The wire encoding used for messages validated against a schema, carried in
+/// the `googclient_schemaencoding` message attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SchemaEncoding {
+    Json,
+    Binary,
+}
+
+impl SchemaEncoding {
+    pub(crate) fn from_attributes(attributes: &std::collections::HashMap<String, String>) -> Option<SchemaEncoding> {
+        match attributes.get("googclient_schemaencoding").map(String::as_str) {
+            Some("JSON") => Some(SchemaEncoding::Json),
+            Some("BINARY") => Some(SchemaEncoding::Binary),
+            _ => None,
+        }
+    }
+}
+
+/// The schema bound to a subscription, used to validate and decode incoming
+/// messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaConfig {
+    pub(crate) schema_type: SchemaType,
+    pub(crate) definition: String,
+}
+
+impl SchemaConfig {
+    /// Create a new schema configuration from its type and definition.
+    pub fn new(schema_type: SchemaType, definition: impl Into<String>) -> SchemaConfig {
+        SchemaConfig {
+            schema_type,
+            definition: definition.into(),
+        }
+    }
+
+    /// The schema's type.
+    pub fn schema_type(&self) -> SchemaType {
+        self.schema_type
+    }
+
+    /// The schema's definition (a `.proto` source or an Avro JSON schema).
+    pub fn definition(&self) -> &str {
+        &self.definition
+    }
+}
+
+/// An error decoding a message against its bound schema.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The subscription has no schema attached.
+    NoSchema,
+    /// The subscription's schema is not of the kind the caller asked to decode.
+    WrongSchemaType,
+    /// The message carries an encoding this client doesn't recognize.
+    UnknownEncoding,
+    /// The payload could not be decoded with the bound schema.
+    Invalid(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::NoSchema => write!(f, "message has no schema attached"),
+            DecodeError::WrongSchemaType => write!(f, "schema is not of the requested type"),
+            DecodeError::UnknownEncoding => write!(f, "message has an unrecognized schema encoding"),
+            DecodeError::Invalid(msg) => write!(f, "invalid message payload: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
@@ -1,8 +1,17 @@
 use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
 
 use chrono::Duration;
 
 use crate::pubsub::api;
+use crate::pubsub::flow::FlowController;
+pub use crate::pubsub::flow::FlowControlOptions;
+use crate::pubsub::lease::LeaseManager;
+use crate::pubsub::order::OrderingController;
+use crate::pubsub::schema;
+use crate::pubsub::schema::SchemaConfig;
 use crate::pubsub::{Client, Error, Message};
 use futures::channel::mpsc::SendError;
 use futures::future::ready;
@@ -11,11 +20,14 @@ use futures::stream::Stream;
 use futures::stream::TryStreamExt;
 
 /// Represents the subscription's configuration.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct SubscriptionConfig {
     pub(crate) ack_deadline_duration: Duration,
     pub(crate) message_retention_duration: Option<Duration>,
     pub(crate) labels: HashMap<String, String>,
+    pub(crate) dead_letter_policy: Option<api::DeadLetterPolicy>,
+    pub(crate) retry_policy: Option<api::RetryPolicy>,
+    pub(crate) enable_message_ordering: bool,
 }
 
 impl SubscriptionConfig {
@@ -40,6 +52,41 @@ impl SubscriptionConfig {
         self.labels.insert(name.into(), value.into());
         self
     }
+
+    /// Forward undeliverable messages to `dead_letter_topic` (fully
+    /// qualified, e.g. `projects/{project}/topics/{topic}`) once they've
+    /// been delivered `max_delivery_attempts` times without being acked.
+    pub fn dead_letter_policy(
+        mut self,
+        dead_letter_topic: impl Into<String>,
+        max_delivery_attempts: i32,
+    ) -> SubscriptionConfig {
+        self.dead_letter_policy = Some(api::DeadLetterPolicy {
+            dead_letter_topic: dead_letter_topic.into(),
+            max_delivery_attempts,
+        });
+        self
+    }
+
+    /// Enable exponential-backoff redelivery, bounded by `min_backoff` and
+    /// `max_backoff`.
+    pub fn retry_policy(mut self, min_backoff: Duration, max_backoff: Duration) -> SubscriptionConfig {
+        self.retry_policy = Some(api::RetryPolicy {
+            minimum_backoff: Some(duration_to_proto(min_backoff)),
+            maximum_backoff: Some(duration_to_proto(max_backoff)),
+        });
+        self
+    }
+
+    /// Enable ordered delivery: messages published with the same ordering
+    /// key are delivered in publish order. Combine with
+    /// [`Subscription::enable_ordered_delivery`] on the receiving side so
+    /// the client also waits for each key's prior message to be acked
+    /// before handing out the next.
+    pub fn enable_message_ordering(mut self, enable: bool) -> SubscriptionConfig {
+        self.enable_message_ordering = enable;
+        self
+    }
 }
 
 impl Default for SubscriptionConfig {
@@ -48,10 +95,22 @@ impl Default for SubscriptionConfig {
             ack_deadline_duration: Duration::seconds(10),
             message_retention_duration: None,
             labels: HashMap::new(),
+            dead_letter_policy: None,
+            retry_policy: None,
+            enable_message_ordering: false,
         }
     }
 }
 
+/// Convert a `chrono::Duration` into the protobuf `Duration` the Pub/Sub API
+/// expects for retry-policy bounds.
+fn duration_to_proto(duration: Duration) -> prost_types::Duration {
+    prost_types::Duration {
+        seconds: duration.num_seconds(),
+        nanos: (duration.num_nanoseconds().unwrap_or(0) % 1_000_000_000) as i32,
+    }
+}
+
 /// Optional parameters for pull.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ReceiveOptions {
@@ -89,6 +148,9 @@ pub struct Subscription {
     pub(crate) client: Client,
     pub(crate) name: String,
     pub(crate) buffer: VecDeque<api::ReceivedMessage>,
+    pub(crate) schema: Option<Arc<SchemaConfig>>,
+    pub(crate) lease: Option<Arc<LeaseManager>>,
+    pub(crate) ordering: Option<Arc<OrderingController>>,
 }
 
 impl Subscription {
@@ -97,6 +159,9 @@ impl Subscription {
             client,
             name: name.into(),
             buffer: VecDeque::new(),
+            schema: None,
+            lease: None,
+            ordering: None,
         }
     }
 
@@ -105,6 +170,79 @@ impl Subscription {
         self.name.rsplit('/').next().unwrap()
     }
 
+    /// Bind an already-known schema to this subscription, so that received
+    /// messages can be decoded with [`Message::decode_avro`] or
+    /// [`Message::decode_proto`]. Prefer [`Subscription::fetch_schema`] to
+    /// look the schema up from Pub/Sub instead of constructing it by hand.
+    pub fn with_schema(mut self, config: SchemaConfig) -> Subscription {
+        self.schema = Some(Arc::new(config));
+        self
+    }
+
+    /// Fetch the schema bound to `schema_name` (its fully qualified name,
+    /// e.g. `projects/{project}/schemas/{schema}`) from the Pub/Sub Schema
+    /// service and bind it to this subscription, so received messages can
+    /// be decoded with [`Message::decode_avro`]/[`Message::decode_proto`].
+    pub async fn fetch_schema(&mut self, schema_name: impl Into<String>) -> Result<(), Error> {
+        let request = api::GetSchemaRequest {
+            name: schema_name.into(),
+            view: api::schema::View::Full as i32,
+        };
+        let request = self.client.construct_request(request).await?;
+        let response = self.client.schema.get_schema(request).await?;
+        let schema = response.into_inner();
+
+        let schema_type = match schema.r#type() {
+            api::schema::Type::Avro => schema::SchemaType::Avro,
+            api::schema::Type::ProtocolBuffer | api::schema::Type::Unspecified => {
+                schema::SchemaType::ProtocolBuffer
+            }
+        };
+
+        self.schema = Some(Arc::new(SchemaConfig::new(schema_type, schema.definition)));
+        Ok(())
+    }
+
+    /// Opt in to background ack-deadline lease renewal, so messages whose
+    /// handler takes longer than `ack_deadline` aren't redelivered. Leases
+    /// are given up (and the message left to expire) once they've been
+    /// outstanding for longer than `max_total_lease`. Call
+    /// [`Subscription::start_lease_management`] to begin renewing.
+    pub fn enable_lease_management(&mut self, ack_deadline: Duration, max_total_lease: Duration) {
+        self.lease = Some(Arc::new(LeaseManager::new(
+            self.client.clone(),
+            self.name.clone(),
+            ack_deadline.to_std().unwrap_or(std::time::Duration::from_secs(10)),
+            max_total_lease
+                .to_std()
+                .unwrap_or(std::time::Duration::from_secs(600)),
+        )));
+    }
+
+    /// Start the background renewal loop. No-op if lease management hasn't
+    /// been enabled with [`Subscription::enable_lease_management`].
+    pub fn start_lease_management(&self) {
+        if let Some(lease) = &self.lease {
+            lease.start();
+        }
+    }
+
+    /// Stop the background renewal loop. Already-tracked leases are kept
+    /// around but no longer renewed.
+    pub fn stop_lease_management(&self) {
+        if let Some(lease) = &self.lease {
+            lease.stop();
+        }
+    }
+
+    /// Opt in to ordered delivery on the client side: messages sharing an
+    /// ordering key are withheld until the prior message for that key has
+    /// been acked or nacked. Only takes effect if the subscription itself
+    /// has [`SubscriptionConfig::enable_message_ordering`] set.
+    pub fn enable_ordered_delivery(&mut self) {
+        self.ordering = Some(Arc::new(OrderingController::new()));
+    }
+
     /// Receive the next message from the subscription.
     pub async fn receive(&mut self) -> Option<Message> {
         self.receive_with_options(Default::default()).await
@@ -113,9 +251,19 @@ impl Subscription {
     /// Receive the next message from the subscription with options.
     pub async fn receive_with_options(&mut self, opts: ReceiveOptions) -> Option<Message> {
         loop {
+            if let Some(ordering) = &self.ordering {
+                if let Some(message) = ordering.next_ready() {
+                    break Some(message);
+                }
+            }
+
             if let Some(handle) = self.buffer.pop_front() {
+                let delivery_attempt = handle.delivery_attempt;
                 let message = handle.message.unwrap();
                 let timestamp = message.publish_time.unwrap();
+                if let Some(lease) = &self.lease {
+                    lease.track(handle.ack_id.clone());
+                }
                 let message = Message {
                     client: self.client.clone(),
                     subscription_name: self.name.clone(),
@@ -127,8 +275,28 @@ impl Subscription {
                         timestamp.seconds,
                         timestamp.nanos as u32,
                     ),
+                    delivery_attempt,
+                    ordering_key: if message.ordering_key.is_empty() {
+                        None
+                    } else {
+                        Some(message.ordering_key)
+                    },
+                    schema: self.schema.clone(),
+                    lease: self.lease.clone(),
+                    flow: None,
+                    ordering: self.ordering.clone(),
                 };
-                break Some(message);
+
+                match &self.ordering {
+                    Some(ordering) => {
+                        let key = message.ordering_key.clone();
+                        if let Some(message) = ordering.offer(key, message) {
+                            break Some(message);
+                        }
+                        // Buffered behind its key's in-flight predecessor; keep looking.
+                    }
+                    None => break Some(message),
+                }
             } else if let Ok(messages) = self.pull(&opts).await {
                 if messages.is_empty() && opts.return_immediately {
                     break None;
@@ -188,6 +356,8 @@ impl Subscription {
 
         let client = self.client.clone();
         let sub_name = self.name.clone();
+        let schema = self.schema.clone();
+        let lease = self.lease.clone();
 
         let sender = sender.with(move |opts: ReceiveStreamOptions| {
             ready(Ok::<_, SendError>(api::StreamingPullRequest {
@@ -207,8 +377,12 @@ impl Subscription {
                 v.received_messages
                     .into_iter()
                     .map(|handle| {
+                        let delivery_attempt = handle.delivery_attempt;
                         let msg = handle.message.unwrap();
                         let timestamp = msg.publish_time.unwrap();
+                        if let Some(lease) = &lease {
+                            lease.track(handle.ack_id.clone());
+                        }
                         Message {
                             client: client.clone(),
                             subscription_name: sub_name.clone(),
@@ -220,6 +394,18 @@ impl Subscription {
                                 timestamp.seconds,
                                 timestamp.nanos as u32,
                             ),
+                            delivery_attempt,
+                            ordering_key: if msg.ordering_key.is_empty() {
+                                None
+                            } else {
+                                Some(msg.ordering_key)
+                            },
+                            schema: schema.clone(),
+                            lease: lease.clone(),
+                            flow: None,
+                            // Ordering is only enforced by `Subscription::receive`/`subscribe`,
+                            // which drive the buffering this primitive doesn't do.
+                            ordering: None,
                         }
                     })
                     .collect()
@@ -228,19 +414,118 @@ impl Subscription {
 
         Ok((response, sender))
     }
+
+    /// Subscribe to this subscription as a flow-controlled `futures::Stream`
+    /// of messages, backed by a streaming pull. The stream stops requesting
+    /// more messages once the `flow_control` limits are reached, and
+    /// resumes automatically once enough outstanding messages have been
+    /// acked or nacked to fall back under them.
+    pub async fn subscribe(
+        &mut self,
+        flow_control: FlowControlOptions,
+    ) -> Result<SubscribeStream<impl Stream<Item = Result<Vec<Message>, Error>>>, Error> {
+        let (stream, _sender) = self.pull_streaming(ReceiveStreamOptions::default()).await?;
+
+        Ok(SubscribeStream {
+            inner: stream,
+            buffer: VecDeque::new(),
+            flow: Arc::new(FlowController::new(flow_control)),
+            ordering: self.ordering.clone(),
+        })
+    }
 }
 
-// impl<'a> Stream for Subscription<'a> {
-//     type Item = Message<'a>;
-//     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-//         let fut = match self.fut {
-//             Some(fut) => fut.as_mut(),
-//             None => {
-//                 self.fut.replace(Box::pin(self.next_message()));
-//                 self.fut.as_mut().unwrap().as_mut()
-//             }
-//         };
-
-//         fut.poll(cx)
-//     }
-// }
+impl Default for ReceiveStreamOptions {
+    fn default() -> Self {
+        ReceiveStreamOptions {
+            ack_ids: Vec::new(),
+            modify_deadline_ack_ids: Vec::new(),
+            modify_deadline_seconds: Vec::new(),
+            stream_ack_deadline_seconds: 0,
+        }
+    }
+}
+
+/// A flow-controlled stream of [`Message`]s, created with
+/// [`Subscription::subscribe`].
+pub struct SubscribeStream<S> {
+    inner: S,
+    buffer: VecDeque<Message>,
+    flow: Arc<FlowController>,
+    ordering: Option<Arc<OrderingController>>,
+}
+
+impl<S> Stream for SubscribeStream<S>
+where
+    S: Stream<Item = Result<Vec<Message>, Error>> + Unpin,
+{
+    type Item = Result<Message, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(ordering) = &self.ordering {
+                if let Some(message) = ordering.next_ready() {
+                    return Poll::Ready(Some(Ok(message)));
+                }
+            }
+
+            if let Some(message) = self.buffer.pop_front() {
+                return Poll::Ready(Some(Ok(message)));
+            }
+
+            if !self.flow.has_capacity() && !self.flow.poll_capacity(cx.waker()) {
+                return Poll::Pending;
+            }
+
+            match Pin::new(&mut self.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(messages))) => {
+                    for mut message in messages {
+                        self.flow.reserve(message.data().len());
+                        message.flow = Some(self.flow.clone());
+                        message.ordering = self.ordering.clone();
+
+                        match &self.ordering {
+                            Some(ordering) => {
+                                let key = message.ordering_key.clone();
+                                if let Some(message) = ordering.offer(key, message) {
+                                    self.buffer.push_back(message);
+                                }
+                                // Buffered behind its key's in-flight predecessor.
+                            }
+                            None => self.buffer.push_back(message),
+                        }
+                    }
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_second_duration_has_no_nanos() {
+        let proto = duration_to_proto(Duration::seconds(5));
+        assert_eq!(proto.seconds, 5);
+        assert_eq!(proto.nanos, 0);
+    }
+
+    #[test]
+    fn fractional_duration_splits_into_seconds_and_nanos() {
+        let proto = duration_to_proto(Duration::milliseconds(1_500));
+        assert_eq!(proto.seconds, 1);
+        assert_eq!(proto.nanos, 500_000_000);
+    }
+
+    #[test]
+    fn sub_second_duration_has_zero_seconds() {
+        let proto = duration_to_proto(Duration::milliseconds(250));
+        assert_eq!(proto.seconds, 0);
+        assert_eq!(proto.nanos, 250_000_000);
+    }
+}